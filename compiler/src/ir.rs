@@ -1,10 +1,15 @@
 use indexmap::IndexSet;
+use num_bigint::BigInt;
+use num_integer::Integer as _;
 use rustpython_bytecode::bytecode::{
-    CodeFlags, CodeObject, ConstantData, Instruction, Label, Location,
+    BinaryOperator, CodeFlags, CodeObject, ComparisonOperator, ConstantData, Instruction, Label,
+    Location, UnaryOperator,
 };
+use std::collections::HashSet;
 
 pub type BlockIdx = Label;
 
+#[derive(Clone)]
 pub struct InstructionInfo {
     /// If the instruction has a Label argument, it's actually a BlockIdx, not a code offset
     pub instr: Instruction,
@@ -41,14 +46,111 @@ pub struct CodeInfo {
     pub cellvar_cache: IndexSet<String>,
     pub freevar_cache: IndexSet<String>,
 }
+/// A single independent transform over a `CodeInfo`, run to a fixpoint by
+/// `CodeInfo::run_passes` the way rustc's MIR pipeline composes its passes.
+/// `run` applies the transform once and reports whether it changed anything,
+/// so the pipeline knows whether another round is worth doing.
+pub trait OptPass {
+    fn run(&self, code: &mut CodeInfo) -> bool;
+}
+
+struct DeadCodeTruncation;
+impl OptPass for DeadCodeTruncation {
+    fn run(&self, code: &mut CodeInfo) -> bool {
+        code.dce()
+    }
+}
+
+struct UnreachableBlockElimination;
+impl OptPass for UnreachableBlockElimination {
+    fn run(&self, code: &mut CodeInfo) -> bool {
+        code.eliminate_unreachable_blocks()
+    }
+}
+
+struct JumpThreading;
+impl OptPass for JumpThreading {
+    fn run(&self, code: &mut CodeInfo) -> bool {
+        code.thread_jumps()
+    }
+}
+
+struct PeepholeConstantFolding;
+impl OptPass for PeepholeConstantFolding {
+    fn run(&self, code: &mut CodeInfo) -> bool {
+        code.peephole_optimize()
+    }
+}
+
+/// The passes `finalize_code` runs for a given `optimize` level, in the order
+/// they run each round. `optimize == 1` gets the cheap structural passes;
+/// `optimize >= 2` adds the peephole constant folder on top, mirroring how
+/// `rustc -O` enables progressively more aggressive MIR passes at higher
+/// levels. Embedders can run more passes on top of this list by passing their
+/// own `OptPass`es to `finalize_code_with_passes`'s `extra_passes`.
+fn default_passes(optimize: u8) -> Vec<Box<dyn OptPass>> {
+    let mut passes: Vec<Box<dyn OptPass>> = Vec::new();
+    if optimize > 0 {
+        passes.push(Box::new(DeadCodeTruncation));
+        passes.push(Box::new(UnreachableBlockElimination));
+        passes.push(Box::new(JumpThreading));
+    }
+    if optimize > 1 {
+        passes.push(Box::new(PeepholeConstantFolding));
+    }
+    passes
+}
+
+/// Upper bound on pipeline rounds, so a pathological sequence of passes that
+/// keep "changing" each other (there shouldn't be one) can't hang `finalize_code`
+/// instead of simply converging one round short of ideal.
+const MAX_PASS_ROUNDS: usize = 16;
+
 impl CodeInfo {
-    pub fn finalize_code(mut self, optimize: u8) -> CodeObject {
-        let max_stacksize = self.max_stacksize();
+    /// Run `passes` over `self` to a fixpoint: keep looping over the full pass
+    /// list as long as some pass in the last round reported a change, bounded
+    /// by `MAX_PASS_ROUNDS` to guarantee termination.
+    fn run_passes(&mut self, passes: &[Box<dyn OptPass>]) {
+        for _ in 0..MAX_PASS_ROUNDS {
+            let mut changed = false;
+            for pass in passes {
+                changed |= pass.run(self);
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Same as `finalize_code_with_passes`, running just the built-in
+    /// `default_passes` pipeline with no embedder-supplied passes.
+    ///
+    /// Note: this always emits one full `Location` per instruction into
+    /// `CodeObject::locations` (dense, not delta-encoded). A compact
+    /// run-length location table (shangzunkk/RustPython#chunk0-5) needs a
+    /// new field and decoder on `rustpython_bytecode::bytecode::CodeObject`
+    /// itself, which lives outside this crate; it isn't implemented here.
+    pub fn finalize_code(self, optimize: u8) -> CodeObject {
+        self.finalize_code_with_passes(optimize, Vec::new())
+    }
+
+    /// `extra_passes` lets an embedder register custom `OptPass`es to run
+    /// alongside the built-in pipeline (`default_passes`), in the order given,
+    /// after the built-ins each round.
+    pub fn finalize_code_with_passes(
+        mut self,
+        optimize: u8,
+        extra_passes: Vec<Box<dyn OptPass>>,
+    ) -> CodeObject {
         let cell2arg = self.cell2arg();
 
-        if optimize > 0 {
-            self.dce();
-        }
+        let mut passes = default_passes(optimize);
+        passes.extend(extra_passes);
+        self.run_passes(&passes);
+
+        // Computed after the pipeline so dropped unreachable blocks, truncated
+        // dead tails, and folded instruction windows don't inflate the estimate.
+        let max_stacksize = self.max_stacksize();
 
         let CodeInfo {
             flags,
@@ -147,7 +249,9 @@ impl CodeInfo {
         }
     }
 
-    fn dce(&mut self) {
+    /// Returns whether any block was actually truncated.
+    fn dce(&mut self) -> bool {
+        let mut changed = false;
         for block in &mut self.blocks {
             let mut last_instr = None;
             for (i, ins) in block.instructions.iter().enumerate() {
@@ -157,74 +261,494 @@ impl CodeInfo {
                 }
             }
             if let Some(i) = last_instr {
+                if i + 1 < block.instructions.len() {
+                    changed = true;
+                }
                 block.instructions.truncate(i + 1);
             }
         }
+        changed
     }
 
-    // TODO: don't use SetupFinally for handling continue/break unwinding, creates
-    // too much confusion in stack analysis
-    // #[allow(unused)]
-    fn max_stacksize(&self) -> u32 {
-        let mut maxdepth = 0;
-        let mut stack = Vec::with_capacity(self.blocks.len());
-        let mut startdepths = vec![0; self.blocks.len()];
-        // TODO: 'seen' is kind of a copout for resolving cycles, and it might not even be correct?
-        let mut seen = vec![false; self.blocks.len()];
-        stack.push((Label(0), 0));
-        'process_blocks: while let Some((block, blockorder)) = stack.pop() {
-            if seen[block.0 as usize] {
+    /// Find every block reachable from `Label(0)`, following both fall-through
+    /// (the next entry in `block_order`) and jump/handler targets (`label_arg()`),
+    /// modeled on rustc's `basic_blocks` reachability analysis.
+    fn reachable_blocks(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        if self.block_order.is_empty() {
+            return reachable;
+        }
+        let mut stack = vec![Label(0)];
+        while let Some(idx) = stack.pop() {
+            let i = idx.0 as usize;
+            if reachable[i] {
+                continue;
+            }
+            reachable[i] = true;
+
+            let block = &self.blocks[i];
+            for ins in &block.instructions {
+                if let Some(&target) = ins.instr.label_arg() {
+                    stack.push(target);
+                }
+            }
+
+            let falls_through = !block
+                .instructions
+                .last()
+                .map_or(false, |ins| ins.instr.unconditional_branch());
+            if falls_through {
+                if let Some(pos) = self.block_order.iter().position(|x| *x == idx) {
+                    if let Some(&next) = self.block_order.get(pos + 1) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Drop blocks that nothing jumps to (and that aren't reached by fall-through),
+    /// shrinking both the emitted code and `max_stacksize`'s search space. This
+    /// physically removes the dropped blocks from `self.blocks` (not just
+    /// `self.block_order`) and remaps every remaining `BlockIdx` — both in
+    /// `block_order` and in every instruction's `label_arg` — to the compacted
+    /// indices, so `self.blocks.len() == self.block_order.len()` still holds
+    /// for `finalize_code`'s assert. Returns whether any block was dropped.
+    fn eliminate_unreachable_blocks(&mut self) -> bool {
+        let reachable = self.reachable_blocks();
+        if reachable.iter().all(|&r| r) {
+            return false;
+        }
+
+        let mut remap = vec![None; self.blocks.len()];
+        let mut new_blocks = Vec::with_capacity(self.blocks.len());
+        for (old_idx, &keep) in reachable.iter().enumerate() {
+            if keep {
+                remap[old_idx] = Some(Label(new_blocks.len() as u32));
+                new_blocks.push(std::mem::take(&mut self.blocks[old_idx]));
+            }
+        }
+
+        for block in &mut new_blocks {
+            for ins in &mut block.instructions {
+                if let Some(l) = ins.instr.label_arg_mut() {
+                    *l = remap[l.0 as usize].expect("jump target must be reachable");
+                }
+            }
+        }
+        self.blocks = new_blocks;
+
+        self.block_order
+            .retain(|idx| remap[idx.0 as usize].is_some());
+        for idx in &mut self.block_order {
+            *idx = remap[idx.0 as usize].unwrap();
+        }
+
+        true
+    }
+
+    /// If `block` is nothing but a single unconditional `Jump`, i.e. a trampoline
+    /// that exists only to redirect to another block, return that block's target.
+    /// A block carrying exception-handler setup (`SetupFinally`/`SetupExcept`) is
+    /// never mistaken for a trampoline: those instructions aren't `Jump`s, so a
+    /// block containing one fails the single-instruction check below.
+    fn trampoline_target(block: &Block) -> Option<Label> {
+        match block.instructions.as_slice() {
+            [InstructionInfo {
+                instr: Instruction::Jump { target },
+                ..
+            }] => Some(*target),
+            _ => None,
+        }
+    }
+
+    /// For every block, follow its chain of trampoline jumps to the final,
+    /// non-trampoline target, breaking on a cycle (an infinite loop of empty
+    /// `Jump`s, however unlikely) rather than looping forever.
+    fn jump_threading_targets(&self) -> Vec<Label> {
+        (0..self.blocks.len())
+            .map(|i| {
+                let mut visited = HashSet::new();
+                let mut cur = Label(i as u32);
+                while visited.insert(cur) {
+                    match Self::trampoline_target(&self.blocks[cur.0 as usize]) {
+                        Some(next) => cur = next,
+                        None => break,
+                    }
+                }
+                cur
+            })
+            .collect()
+    }
+
+    /// Rewrite every jump/handler target to point directly at the end of its
+    /// trampoline chain, so a dispatch is no longer wasted on single-`Jump`
+    /// blocks that only exist to redirect elsewhere. The trampoline blocks
+    /// themselves are left in place; once nothing points at them any more,
+    /// `eliminate_unreachable_blocks` drops them. Returns whether any target
+    /// actually moved.
+    fn thread_jumps(&mut self) -> bool {
+        let targets = self.jump_threading_targets();
+        let mut changed = false;
+        for block in &mut self.blocks {
+            for ins in &mut block.instructions {
+                if let Some(l) = ins.instr.label_arg_mut() {
+                    let threaded = targets[l.0 as usize];
+                    if threaded != *l {
+                        changed = true;
+                    }
+                    *l = threaded;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Rewrite each block's instruction windows to a fixpoint: constant-fold
+    /// `LoadConst, LoadConst, BinaryOp`/`LoadConst, UnaryOp`, collapse runs of
+    /// `LoadConst`s feeding a `BuildTuple` into one tuple constant, and fuse a
+    /// comparison immediately followed by `UnaryNot` into the negated comparison.
+    /// Cascaded folds (e.g. `1 + 2 * 3`) collapse fully because a changed block
+    /// is re-scanned from the top until a pass makes no further progress.
+    /// Returns whether anything in any block was folded.
+    fn peephole_optimize(&mut self) -> bool {
+        let mut changed = false;
+        for idx in 0..self.blocks.len() {
+            while self.optimize_block_once(idx) {
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn optimize_block_once(&mut self, block_idx: usize) -> bool {
+        let instructions = std::mem::take(&mut self.blocks[block_idx].instructions);
+        let mut out: Vec<InstructionInfo> = Vec::with_capacity(instructions.len());
+        let mut changed = false;
+        let mut i = 0;
+        while i < instructions.len() {
+            if let Some((folded, consumed)) = self.try_fold_binary_op(&instructions, i) {
+                out.push(folded);
+                i += consumed;
+                changed = true;
+                continue;
+            }
+            if let Some((folded, consumed)) = self.try_fold_unary_op(&instructions, i) {
+                out.push(folded);
+                i += consumed;
+                changed = true;
                 continue;
             }
-            seen[block.0 as usize] = true;
-            let mut depth = startdepths[block.0 as usize];
-            for i in &self.blocks[block.0 as usize].instructions {
+            if let Some((skip, folded, consumed)) = self.try_fold_build_tuple(&instructions, i) {
+                out.extend_from_slice(&instructions[i..i + skip]);
+                out.push(folded);
+                i += consumed;
+                changed = true;
+                continue;
+            }
+            if let Some(consumed) = try_fuse_negated_comparison(&instructions, i, &mut out) {
+                i += consumed;
+                changed = true;
+                continue;
+            }
+            out.push(instructions[i].clone());
+            i += 1;
+        }
+        self.blocks[block_idx].instructions = out;
+        changed
+    }
+
+    /// `LoadConst a; LoadConst b; BinaryOperation(op)` -> `LoadConst (a op b)`,
+    /// when both operands are constants the operator can be folded on without
+    /// risking a runtime exception (e.g. division by zero is left alone so
+    /// Python's `ZeroDivisionError` still raises where the program expects it).
+    fn try_fold_binary_op(
+        &mut self,
+        instructions: &[InstructionInfo],
+        i: usize,
+    ) -> Option<(InstructionInfo, usize)> {
+        let lhs = instructions.get(i)?;
+        let rhs = instructions.get(i + 1)?;
+        let op_instr = instructions.get(i + 2)?;
+        let lhs_const = self.load_const(&lhs.instr)?;
+        let rhs_const = self.load_const(&rhs.instr)?;
+        let (op, inplace) = match &op_instr.instr {
+            Instruction::BinaryOperation { op, inplace } => (*op, *inplace),
+            _ => return None,
+        };
+        if inplace {
+            // an in-place op can mutate a shared constant object at runtime; don't fold it.
+            return None;
+        }
+        let folded = fold_binary_op(op, lhs_const, rhs_const)?;
+        let idx = self.add_constant(folded);
+        Some((
+            InstructionInfo {
+                instr: Instruction::LoadConst { idx },
+                location: lhs.location,
+            },
+            3,
+        ))
+    }
+
+    /// `LoadConst a; UnaryOperation(op)` -> `LoadConst (op a)`.
+    fn try_fold_unary_op(
+        &mut self,
+        instructions: &[InstructionInfo],
+        i: usize,
+    ) -> Option<(InstructionInfo, usize)> {
+        let operand = instructions.get(i)?;
+        let op_instr = instructions.get(i + 1)?;
+        let operand_const = self.load_const(&operand.instr)?;
+        let op = match &op_instr.instr {
+            Instruction::UnaryOperation { op } => *op,
+            _ => return None,
+        };
+        let folded = fold_unary_op(op, operand_const)?;
+        let idx = self.add_constant(folded);
+        Some((
+            InstructionInfo {
+                instr: Instruction::LoadConst { idx },
+                location: operand.location,
+            },
+            2,
+        ))
+    }
+
+    /// A `BuildTuple(size)` (no unpacking) is fed by exactly the `size`
+    /// `LoadConst`s immediately preceding it; if those are constants, fold
+    /// them into a single `LoadConst` of a `ConstantData::Tuple`. Only the
+    /// trailing `size` consts of the run starting at `i` are matched (an
+    /// earlier `LoadConst` in a longer run may be unrelated to this tuple,
+    /// left on the stack for later), so the returned `skip` tells the caller
+    /// how many leading instructions from `i` to copy through unchanged
+    /// before the folded tuple load.
+    ///
+    /// Returns `(skip, folded, total_consumed)`.
+    fn try_fold_build_tuple(
+        &mut self,
+        instructions: &[InstructionInfo],
+        i: usize,
+    ) -> Option<(usize, InstructionInfo, usize)> {
+        let mut j = i;
+        while self.load_const(&instructions.get(j)?.instr).is_some() {
+            j += 1;
+        }
+        let run_len = j - i;
+        let build_instr = instructions.get(j)?;
+        let size = match &build_instr.instr {
+            Instruction::BuildTuple { size, unpack } if !*unpack => *size,
+            _ => return None,
+        };
+        if size == 0 || size > run_len {
+            return None;
+        }
+        let start = j - size;
+        let skip = start - i;
+        let elements = instructions[start..j]
+            .iter()
+            .map(|ins| self.load_const(&ins.instr).unwrap().clone())
+            .collect();
+        let idx = self.add_constant(ConstantData::Tuple { elements });
+        Some((
+            skip,
+            InstructionInfo {
+                instr: Instruction::LoadConst { idx },
+                location: instructions[start].location,
+            },
+            run_len + 1,
+        ))
+    }
+
+    /// Append `value` to the constant pool, reusing an existing equal entry
+    /// instead of duplicating it.
+    fn add_constant(&mut self, value: ConstantData) -> usize {
+        if let Some(idx) = self.constants.iter().position(|c| *c == value) {
+            return idx;
+        }
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    /// The `ConstantData` an instruction pushes, if it's a `LoadConst`.
+    fn load_const(&self, instr: &Instruction) -> Option<&ConstantData> {
+        match instr {
+            Instruction::LoadConst { idx } => self.constants.get(*idx),
+            _ => None,
+        }
+    }
+
+    /// Worklist-based abstract interpretation of stack depth: `startdepths[entry]`
+    /// begins at 0 and every block is re-simulated from its (possibly revised)
+    /// start depth whenever `stackdepth_push` discovers a path that reaches it
+    /// deeper than before, until the worklist drains and no start depth can
+    /// increase any further. Unlike a `seen`-guarded single pass, a block on a
+    /// loop back-edge gets reprocessed as many times as its start depth grows,
+    /// so the result doesn't depend on visit order.
+    ///
+    /// `SetupFinally`/`SetupExcept` fall through to normal execution but also
+    /// have a `label_arg()` handler target; `stack_effect(true)` reports how
+    /// many values the setup instruction pushes for the *handler*, so the
+    /// handler's start depth is exactly `depth_at_setup + handler_extra` rather
+    /// than whatever the fall-through path computes, and the handler block's
+    /// start depth is seeded correctly without any special-casing here.
+    fn max_stacksize(&self) -> u32 {
+        let mut maxdepth = 0;
+        let mut worklist = Vec::with_capacity(self.blocks.len());
+        // i32::MIN marks a block not reached yet, so the first path into it
+        // (even at depth 0) enqueues it.
+        let mut startdepths = vec![i32::MIN; self.blocks.len()];
+        startdepths[0] = 0;
+        worklist.push(Label(0));
+        while let Some(block_idx) = worklist.pop() {
+            let mut depth = startdepths[block_idx.0 as usize];
+            let mut falls_through = true;
+            for i in &self.blocks[block_idx.0 as usize].instructions {
                 let instr = &i.instr;
-                let effect = instr.stack_effect(false);
-                let new_depth = depth + effect;
+                let new_depth = depth + instr.stack_effect(false);
                 if new_depth > maxdepth {
                     maxdepth = new_depth
                 }
                 if let Some(&target_block) = instr.label_arg() {
-                    let effect = instr.stack_effect(true);
-                    let target_depth = depth + effect;
+                    let target_depth = depth + instr.stack_effect(true);
                     if target_depth > maxdepth {
                         maxdepth = target_depth
                     }
-                    stackdepth_push(
-                        &mut stack,
-                        &mut startdepths,
-                        (target_block, u32::MAX),
-                        target_depth,
-                    );
+                    stackdepth_push(&mut worklist, &mut startdepths, target_block, target_depth);
                 }
                 depth = new_depth;
                 if instr.unconditional_branch() {
-                    continue 'process_blocks;
+                    falls_through = false;
+                    break;
                 }
             }
-            seen[block.0 as usize] = false;
-            let next_blockorder = if blockorder == u32::MAX {
-                self.block_order.iter().position(|x| *x == block).unwrap() as u32 + 1
-            } else {
-                blockorder + 1
-            };
-            let next = self.block_order[next_blockorder as usize];
-            stackdepth_push(&mut stack, &mut startdepths, (next, next_blockorder), depth);
+            if falls_through {
+                let pos = self
+                    .block_order
+                    .iter()
+                    .position(|x| *x == block_idx)
+                    .unwrap();
+                let next = self.block_order[pos + 1];
+                stackdepth_push(&mut worklist, &mut startdepths, next, depth);
+            }
         }
         maxdepth as u32
     }
 }
 
-fn stackdepth_push(
-    stack: &mut Vec<(Label, u32)>,
-    startdepths: &mut [i32],
-    target: (Label, u32),
-    depth: i32,
-) {
-    let block_depth = &mut startdepths[target.0 .0 as usize];
+fn stackdepth_push(worklist: &mut Vec<Label>, startdepths: &mut [i32], target: Label, depth: i32) {
+    let block_depth = &mut startdepths[target.0 as usize];
     if depth > *block_depth {
         *block_depth = depth;
-        stack.push(target);
+        worklist.push(target);
+    }
+}
+
+fn fold_binary_op(
+    op: BinaryOperator,
+    lhs: &ConstantData,
+    rhs: &ConstantData,
+) -> Option<ConstantData> {
+    use ConstantData::*;
+    Some(match (op, lhs, rhs) {
+        (BinaryOperator::Add, Integer { value: a }, Integer { value: b }) => {
+            Integer { value: a + b }
+        }
+        (BinaryOperator::Subtract, Integer { value: a }, Integer { value: b }) => {
+            Integer { value: a - b }
+        }
+        (BinaryOperator::Multiply, Integer { value: a }, Integer { value: b }) => {
+            Integer { value: a * b }
+        }
+        // BigInt's `/`/`%` truncate toward zero (Rust semantics); Python's `//`/`%`
+        // floor toward negative infinity, so `div_floor`/`mod_floor` are required
+        // here — plain `/`/`%` would fold `-7 // 2` to `-3` instead of `-4`.
+        (BinaryOperator::FloorDivide, Integer { value: a }, Integer { value: b })
+            if *b != BigInt::from(0) =>
+        {
+            Integer {
+                value: a.div_floor(b),
+            }
+        }
+        (BinaryOperator::Modulo, Integer { value: a }, Integer { value: b })
+            if *b != BigInt::from(0) =>
+        {
+            Integer {
+                value: a.mod_floor(b),
+            }
+        }
+        (BinaryOperator::Add, Float { value: a }, Float { value: b }) => Float { value: a + b },
+        (BinaryOperator::Subtract, Float { value: a }, Float { value: b }) => {
+            Float { value: a - b }
+        }
+        (BinaryOperator::Multiply, Float { value: a }, Float { value: b }) => {
+            Float { value: a * b }
+        }
+        (BinaryOperator::Divide, Float { value: a }, Float { value: b }) if *b != 0.0 => {
+            Float { value: a / b }
+        }
+        (BinaryOperator::Add, Str { value: a }, Str { value: b }) => Str {
+            value: format!("{}{}", a, b),
+        },
+        // Integer division/modulo by a zero divisor, and float division by zero,
+        // must raise ZeroDivisionError at runtime, so they're deliberately not folded.
+        _ => return None,
+    })
+}
+
+fn fold_unary_op(op: UnaryOperator, operand: &ConstantData) -> Option<ConstantData> {
+    use ConstantData::*;
+    Some(match (op, operand) {
+        (UnaryOperator::Minus, Integer { value: a }) => Integer { value: -a },
+        (UnaryOperator::Minus, Float { value: a }) => Float { value: -a },
+        (UnaryOperator::Invert, Integer { value: a }) => Integer { value: !a },
+        _ => return None,
+    })
+}
+
+/// `op`'s negation, restricted to the comparisons where `not (a op b)` is
+/// genuinely equivalent to `a negate(op) b` for arbitrary operands.
+///
+/// `Is`/`IsNot` and `In`/`NotIn` qualify: identity comparison never invokes
+/// user code, and `in`/`not in` are defined as exact opposites of each other
+/// by the data model. `Equal`/`NotEqual`/`Less`/`LessOrEqual`/`Greater`/
+/// `GreaterOrEqual` do NOT qualify, even though they look like mirror images:
+/// `==`/`!=` dispatch to independent `__eq__`/`__ne__` methods that can give
+/// inconsistent answers, and the ordering comparisons break under NaN (`not
+/// (nan < 1.0)` is `True`, but `nan >= 1.0` is `False`). CPython deliberately
+/// doesn't fuse those either.
+fn negate_comparison(op: ComparisonOperator) -> Option<ComparisonOperator> {
+    use ComparisonOperator::*;
+    Some(match op {
+        In => NotIn,
+        NotIn => In,
+        Is => IsNot,
+        IsNot => Is,
+        Equal | NotEqual | Less | LessOrEqual | Greater | GreaterOrEqual => return None,
+    })
+}
+
+/// `CompareOperation(op); UnaryNot` -> `CompareOperation(negate(op))`, for the
+/// `Is`/`IsNot`/`In`/`NotIn` comparisons `negate_comparison` actually handles.
+fn try_fuse_negated_comparison(
+    instructions: &[InstructionInfo],
+    i: usize,
+    out: &mut Vec<InstructionInfo>,
+) -> Option<usize> {
+    let cmp = instructions.get(i)?;
+    let not_instr = instructions.get(i + 1)?;
+    if !matches!(not_instr.instr, Instruction::UnaryNot) {
+        return None;
     }
+    let op = match &cmp.instr {
+        Instruction::CompareOperation { op } => *op,
+        _ => return None,
+    };
+    let negated = negate_comparison(op)?;
+    out.push(InstructionInfo {
+        instr: Instruction::CompareOperation { op: negated },
+        location: cmp.location,
+    });
+    Some(2)
 }